@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+use toml;
+use ::error::Error;
+use ::make_task::MakeTask;
+use ::ansible_task::AnsibleTask;
+use ::verified_path::VerifiedPath;
+use ::repo_config::{Defaults, lookup_as_string, LookupResult};
+use ::deploy_backend::{DeployBackend, DeployTask};
+use ::interpolate::{interpolate, InterpolationContext};
+
+fn interpolated(raw: &str,
+                 branch_name: &str,
+                 project_root: &Path,
+                 field: &'static str)
+                 -> Result<String, Error> {
+    let ctx = InterpolationContext { branch: Some(branch_name), project_root: project_root };
+    interpolate(raw, &ctx).map_err(|err| Error {
+        desc: err.desc,
+        subject: Some(format!("branch.{}.{}", branch_name, field)),
+    })
+}
+
+struct AnsibleBackend;
+
+impl DeployBackend for AnsibleBackend {
+    fn name(&self) -> &str {
+        "ansible"
+    }
+
+    fn parse_branch<'a>(&self,
+                         branch_name: &str,
+                         table: &toml::Value,
+                         project_root: &'a Path,
+                         defaults: &Defaults<'a>)
+                         -> Result<Box<DeployTask + 'a>, Error> {
+        let playbook = match lookup_as_string(table, "playbook") {
+            LookupResult::Missing => None,
+            LookupResult::WrongType => return Err(Error {
+                desc: "branch 'playbook' not a string",
+                subject: Some(format!("branch.{}.playbook", branch_name)),
+            }),
+            LookupResult::Value(v) => {
+                let v = interpolated(v, branch_name, project_root, "playbook")?;
+                match VerifiedPath::file(Some(project_root), Path::new(&v)) {
+                    Ok(v) => Some(v),
+                    Err(err) => return Err(err),
+                }
+            }
+        };
+        let inventory = match lookup_as_string(table, "inventory") {
+            LookupResult::Missing => None,
+            LookupResult::WrongType => return Err(Error {
+                desc: "branch 'inventory' not a string",
+                subject: Some(format!("branch.{}.inventory", branch_name)),
+            }),
+            LookupResult::Value(v) => {
+                let v = interpolated(v, branch_name, project_root, "inventory")?;
+                match VerifiedPath::file(Some(project_root), Path::new(&v)) {
+                    Ok(v) => Some(v),
+                    Err(err) => return Err(err),
+                }
+            }
+        };
+
+        let default_playbook = match defaults.playbook {
+            Some(ref raw) => {
+                let v = interpolated(raw, branch_name, project_root, "playbook")?;
+                Some(match VerifiedPath::file(Some(project_root), Path::new(&v)) {
+                    Ok(v) => v,
+                    Err(err) => return Err(err),
+                })
+            }
+            None => None,
+        };
+
+        match (playbook, inventory, default_playbook) {
+            (Some(p), Some(i), _) |
+            (None, Some(i), Some(p)) =>
+                Ok(Box::new(AnsibleTask::new(p.to_string(), i.to_string(), project_root))),
+            (_, _, _) => Err(Error {
+                desc: "could not combine default and branch config to find playbook + inventory combination",
+                subject: None,
+            }),
+        }
+    }
+}
+
+struct MakefileBackend;
+
+impl DeployBackend for MakefileBackend {
+    fn name(&self) -> &str {
+        "makefile"
+    }
+
+    fn parse_branch<'a>(&self,
+                         branch_name: &str,
+                         table: &toml::Value,
+                         project_root: &'a Path,
+                         _defaults: &Defaults<'a>)
+                         -> Result<Box<DeployTask + 'a>, Error> {
+        match lookup_as_string(table, "task") {
+            LookupResult::Missing => Err(Error {
+                desc: "cannot construct a task for branch between local config and defaults",
+                subject: None,
+            }),
+            LookupResult::WrongType => Err(Error {
+                desc: "branch 'task' not a string",
+                subject: Some(format!("branch.{}.task", branch_name)),
+            }),
+            LookupResult::Value(v) => {
+                let v = interpolated(v, branch_name, project_root, "task")?;
+                match MakeTask::new(project_root, &v) {
+                    Ok(v) => Ok(Box::new(v)),
+                    Err(err) => Err(err),
+                }
+            }
+        }
+    }
+}
+
+/// Maps `method` strings from `.deployer.conf` to the `DeployBackend` that
+/// knows how to build a task for it. `new()` holds the two built-in backends
+/// (`ansible`, `makefile`); a host application can `register()` its own
+/// backends (e.g. `shell` or `kubectl`) on top and hand the result to
+/// `RepoConfig::load_with_registry`/`from_str_with_registry` without
+/// patching this module.
+pub struct BackendRegistry {
+    backends: BTreeMap<String, Box<DeployBackend>>,
+}
+
+impl BackendRegistry {
+    pub fn new() -> BackendRegistry {
+        let mut registry = BackendRegistry { backends: BTreeMap::new() };
+        registry.register(Box::new(AnsibleBackend));
+        registry.register(Box::new(MakefileBackend));
+        registry
+    }
+
+    pub fn register(&mut self, backend: Box<DeployBackend>) {
+        let name = backend.name().to_string();
+        self.backends.insert(name, backend);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&DeployBackend> {
+        // "make" has always been accepted as a synonym for "makefile".
+        let name = if name == "make" { "makefile" } else { name };
+        self.backends.get(name).map(|b| &**b)
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.backends.keys().map(|s| s.as_str()).collect()
+    }
+}
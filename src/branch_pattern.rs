@@ -0,0 +1,116 @@
+use glob::Pattern;
+
+/// A compiled `[branches.*]` key, kept alongside its specificity so
+/// `RepoConfig::lookup_branch` doesn't recompile or re-rank patterns on
+/// every call.
+#[derive(Debug)]
+pub struct BranchPattern {
+    key: String,
+    pattern: Pattern,
+    non_wildcard_segments: usize,
+    literal_prefix_len: usize,
+}
+
+impl BranchPattern {
+    pub fn new(key: &str) -> Option<BranchPattern> {
+        let pattern = match Pattern::new(key) {
+            Ok(pattern) => pattern,
+            Err(_) => return None,
+        };
+        Some(BranchPattern {
+            key: String::from(key),
+            pattern: pattern,
+            non_wildcard_segments: non_wildcard_segments(key),
+            literal_prefix_len: literal_prefix_len(key),
+        })
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        self.pattern.matches(name)
+    }
+
+    /// Higher is more specific; compared lexicographically by
+    /// (non-wildcard path segments, literal prefix length).
+    fn specificity(&self) -> (usize, usize) {
+        (self.non_wildcard_segments, self.literal_prefix_len)
+    }
+}
+
+fn is_wildcard_segment(segment: &str) -> bool {
+    segment.contains('*') || segment.contains('?') || segment.contains('[')
+}
+
+fn non_wildcard_segments(key: &str) -> usize {
+    key.split('/').filter(|segment| !is_wildcard_segment(segment)).count()
+}
+
+fn literal_prefix_len(key: &str) -> usize {
+    key.chars().take_while(|&c| c != '*' && c != '?' && c != '[').count()
+}
+
+/// Among the patterns matching `name`, return the most specific one,
+/// i.e. the one with the most non-wildcard path segments, breaking ties
+/// by longest literal prefix.
+pub fn best_match<'p>(patterns: &'p [BranchPattern], name: &str) -> Option<&'p BranchPattern> {
+    patterns.iter()
+        .filter(|pattern| pattern.matches(name))
+        .max_by_key(|pattern| pattern.specificity())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(keys: &[&str]) -> Vec<BranchPattern> {
+        keys.iter().map(|key| BranchPattern::new(key).unwrap()).collect()
+    }
+
+    #[test]
+    fn new_rejects_an_invalid_glob_pattern() {
+        assert!(BranchPattern::new("release[").is_none());
+    }
+
+    #[test]
+    fn matches_follows_glob_semantics() {
+        let pattern = BranchPattern::new("release/*").unwrap();
+        assert!(pattern.matches("release/42"));
+        assert!(!pattern.matches("staging"));
+    }
+
+    #[test]
+    fn best_match_prefers_more_non_wildcard_segments() {
+        let patterns = patterns(&["*", "release/*"]);
+        let best = best_match(&patterns, "release/42").unwrap();
+        assert_eq!(best.key(), "release/*");
+    }
+
+    #[test]
+    fn best_match_breaks_ties_with_longer_literal_prefix() {
+        // Both match "release/x99" and both have one non-wildcard segment
+        // ("release"); "release/x*" has the longer literal prefix and
+        // should win.
+        let patterns = patterns(&["release/*", "release/x*"]);
+        let best = best_match(&patterns, "release/x99").unwrap();
+        assert_eq!(best.key(), "release/x*");
+    }
+
+    #[test]
+    fn best_match_prefers_an_exact_key_over_a_matching_glob() {
+        // An exact key like "release/1.0" has no wildcard segments at all,
+        // so it outranks "release/*" on specificity alone even without the
+        // exact-lookup shortcut `RepoConfig::lookup_branch` takes first.
+        let patterns = patterns(&["release/*", "release/1.0"]);
+        let best = best_match(&patterns, "release/1.0").unwrap();
+        assert_eq!(best.key(), "release/1.0");
+    }
+
+    #[test]
+    fn best_match_returns_none_when_nothing_matches() {
+        let patterns = patterns(&["release/*", "staging"]);
+        assert!(best_match(&patterns, "production").is_none());
+    }
+}
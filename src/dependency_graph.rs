@@ -0,0 +1,120 @@
+use std::collections::BTreeMap;
+use ::error::Error;
+
+/// A branch's absence from `state` means unvisited; only `InProgress` and
+/// `Done` nodes need a sentinel.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    InProgress,
+    Done,
+}
+
+/// Topologically sorts `branch` and the `depends_on` closure reachable
+/// from it, via `edges` (a branch name -> its `depends_on` list), so that
+/// every dependency appears before the branch that needs it. Rejects
+/// cycles and edges that point at a branch key missing from `edges`.
+pub fn resolution_order(edges: &BTreeMap<String, Vec<String>>, branch: &str) -> Result<Vec<String>, Error> {
+    let mut state: BTreeMap<&str, State> = BTreeMap::new();
+    let mut order = Vec::new();
+    let mut stack = Vec::new();
+    visit(edges, branch, &mut state, &mut order, &mut stack)?;
+    Ok(order)
+}
+
+fn visit<'a>(edges: &'a BTreeMap<String, Vec<String>>,
+             name: &'a str,
+             state: &mut BTreeMap<&'a str, State>,
+             order: &mut Vec<String>,
+             stack: &mut Vec<&'a str>)
+             -> Result<(), Error> {
+    match state.get(name) {
+        Some(&State::Done) => return Ok(()),
+        Some(&State::InProgress) => {
+            let cycle_start = stack.iter().position(|n| *n == name).unwrap_or(0);
+            let mut cycle: Vec<&str> = stack[cycle_start..].to_vec();
+            cycle.push(name);
+            return Err(Error {
+                desc: "cycle detected in branch 'depends_on' graph",
+                subject: Some(cycle.join(" -> ")),
+            });
+        }
+        _ => {}
+    }
+
+    let deps = match edges.get(name) {
+        Some(deps) => deps,
+        None => return Err(Error {
+            desc: "'depends_on' references a branch that is not configured",
+            subject: Some(String::from(name)),
+        }),
+    };
+
+    state.insert(name, State::InProgress);
+    stack.push(name);
+    for dep in deps {
+        visit(edges, dep, state, order, stack)?;
+    }
+    stack.pop();
+    state.insert(name, State::Done);
+    order.push(String::from(name));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edges(pairs: &[(&str, &[&str])]) -> BTreeMap<String, Vec<String>> {
+        pairs.iter()
+            .map(|&(name, deps)| {
+                (String::from(name), deps.iter().map(|d| String::from(*d)).collect())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn orders_dependencies_before_dependents() {
+        let edges = edges(&[("a", &["b"]), ("b", &["c"]), ("c", &[])]);
+        let order = resolution_order(&edges, "a").unwrap();
+        assert_eq!(order, vec!["c", "b", "a"]);
+    }
+
+    #[test]
+    fn visits_a_diamond_dependency_only_once() {
+        let edges = edges(&[("a", &["b", "c"]), ("b", &["d"]), ("c", &["d"]), ("d", &[])]);
+        let order = resolution_order(&edges, "a").unwrap();
+        assert_eq!(order, vec!["d", "b", "c", "a"]);
+    }
+
+    #[test]
+    fn rejects_a_direct_cycle() {
+        let edges = edges(&[("a", &["a"])]);
+        let err = resolution_order(&edges, "a").unwrap_err();
+        assert_eq!(err.desc, "cycle detected in branch 'depends_on' graph");
+        assert_eq!(err.subject, Some(String::from("a -> a")));
+    }
+
+    #[test]
+    fn rejects_an_indirect_cycle() {
+        let edges = edges(&[("a", &["b"]), ("b", &["a"])]);
+        let err = resolution_order(&edges, "a").unwrap_err();
+        assert_eq!(err.desc, "cycle detected in branch 'depends_on' graph");
+        assert_eq!(err.subject, Some(String::from("a -> b -> a")));
+    }
+
+    #[test]
+    fn rejects_a_dependency_on_an_unconfigured_branch() {
+        let edges = edges(&[("a", &["missing"])]);
+        let err = resolution_order(&edges, "a").unwrap_err();
+        assert_eq!(err.desc, "'depends_on' references a branch that is not configured");
+        assert_eq!(err.subject, Some(String::from("missing")));
+    }
+
+    #[test]
+    fn rejects_an_unconfigured_root_branch() {
+        let edges = edges(&[("a", &[])]);
+        let err = resolution_order(&edges, "missing").unwrap_err();
+        assert_eq!(err.desc, "'depends_on' references a branch that is not configured");
+        assert_eq!(err.subject, Some(String::from("missing")));
+    }
+}
@@ -0,0 +1,45 @@
+use std::path::Path;
+use toml;
+use ::error::Error;
+use ::make_task::MakeTask;
+use ::ansible_task::AnsibleTask;
+use ::repo_config::Defaults;
+
+/// Something a branch can run to deploy itself, regardless of which
+/// `DeployBackend` produced it.
+pub trait DeployTask {
+    fn describe(&self) -> String;
+}
+
+impl<'a> DeployTask for MakeTask<'a> {
+    fn describe(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl<'a> DeployTask for AnsibleTask<'a> {
+    fn describe(&self) -> String {
+        format!("ansible playbook={} inventory={}", self.playbook, self.inventory)
+    }
+}
+
+/// A pluggable deploy method. Built-in backends live in `backend_registry`;
+/// third parties can implement this trait and register their own under a
+/// new `method` name without touching the core config parser.
+pub trait DeployBackend {
+    /// The `method = "..."` string this backend answers to.
+    fn name(&self) -> &str;
+
+    /// Build the task for a single `[branches.*]` table. `defaults` holds
+    /// the `[defaults]` values an implementation may fall back to where the
+    /// branch omits a field; the built-in `makefile` backend does not use
+    /// it, so a makefile branch without `task` is an error. `branch_name` is the
+    /// matched `[branches.*]` key, available to implementations for
+    /// `${branch}` interpolation and for error subjects.
+    fn parse_branch<'a>(&self,
+                         branch_name: &str,
+                         table: &toml::Value,
+                         project_root: &'a Path,
+                         defaults: &Defaults<'a>)
+                         -> Result<Box<DeployTask + 'a>, Error>;
+}
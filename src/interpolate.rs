@@ -0,0 +1,126 @@
+use std::env;
+use std::path::Path;
+use ::error::Error;
+
+/// What `${...}` placeholders can resolve against: real environment
+/// variables, plus a small set of built-ins (`${branch}`, `${project_root}`)
+/// that aren't in the environment.
+pub struct InterpolationContext<'a> {
+    pub branch: Option<&'a str>,
+    pub project_root: &'a Path,
+}
+
+/// Expands `${VAR}` placeholders in `raw` against `ctx`, e.g. turning
+/// `ansible/${branch}.yml` into `ansible/production.yml`. Unresolved
+/// variables are an error rather than left as literal text, so a typo'd
+/// `${BRANCH}` fails fast instead of silently deploying the wrong file.
+pub fn interpolate(raw: &str, ctx: &InterpolationContext) -> Result<String, Error> {
+    let mut out = String::with_capacity(raw.len());
+    let mut rest = raw;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        let end = match after_open.find('}') {
+            Some(end) => end,
+            None => return Err(Error {
+                desc: "unterminated '${' in config value",
+                subject: None,
+            }),
+        };
+        let var = &after_open[..end];
+        out.push_str(&resolve(var, ctx)?);
+        rest = &after_open[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+fn resolve(var: &str, ctx: &InterpolationContext) -> Result<String, Error> {
+    match var {
+        "branch" => match ctx.branch {
+            Some(branch) => Ok(String::from(branch)),
+            None => Err(Error {
+                desc: "'${branch}' is not available outside of a [branches.*] table",
+                subject: None,
+            }),
+        },
+        "project_root" => Ok(ctx.project_root.to_string_lossy().into_owned()),
+        _ => match env::var(var) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(Error {
+                desc: "could not resolve '${...}' variable in config value",
+                subject: None,
+            }),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn substitutes_project_root() {
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: None, project_root: root };
+        assert_eq!(interpolate("${project_root}/ansible", &ctx).unwrap(), "/srv/app/ansible");
+    }
+
+    #[test]
+    fn substitutes_branch_when_available() {
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: Some("production"), project_root: root };
+        assert_eq!(interpolate("ansible/${branch}.yml", &ctx).unwrap(), "ansible/production.yml");
+    }
+
+    #[test]
+    fn substitutes_multiple_placeholders_in_one_value() {
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: Some("production"), project_root: root };
+        assert_eq!(
+            interpolate("${project_root}/ansible/${branch}.yml", &ctx).unwrap(),
+            "/srv/app/ansible/production.yml"
+        );
+    }
+
+    #[test]
+    fn branch_outside_a_branches_table_is_an_error() {
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: None, project_root: root };
+        let err = interpolate("${branch}", &ctx).unwrap_err();
+        assert_eq!(err.desc, "'${branch}' is not available outside of a [branches.*] table");
+    }
+
+    #[test]
+    fn unterminated_placeholder_is_an_error() {
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: None, project_root: root };
+        let err = interpolate("ansible/${branch.yml", &ctx).unwrap_err();
+        assert_eq!(err.desc, "unterminated '${' in config value");
+    }
+
+    #[test]
+    fn unresolved_env_var_is_an_error() {
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: None, project_root: root };
+        let err = interpolate("${HOOKSHOT_TEST_DEFINITELY_UNSET_VAR}", &ctx).unwrap_err();
+        assert_eq!(err.desc, "could not resolve '${...}' variable in config value");
+    }
+
+    #[test]
+    fn resolves_a_real_environment_variable() {
+        env::set_var("HOOKSHOT_TEST_INTERPOLATE_VAR", "hello");
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: None, project_root: root };
+        assert_eq!(interpolate("${HOOKSHOT_TEST_INTERPOLATE_VAR}", &ctx).unwrap(), "hello");
+        env::remove_var("HOOKSHOT_TEST_INTERPOLATE_VAR");
+    }
+
+    #[test]
+    fn leaves_text_without_placeholders_untouched() {
+        let root = Path::new("/srv/app");
+        let ctx = InterpolationContext { branch: None, project_root: root };
+        assert_eq!(interpolate("ansible/deploy.yml", &ctx).unwrap(), "ansible/deploy.yml");
+    }
+}
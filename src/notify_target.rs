@@ -0,0 +1,137 @@
+use ::error::Error;
+
+/// A deploy runner's notification sink, parsed once at config-load time
+/// rather than re-parsed from a raw string at send time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyTarget {
+    /// `http://` / `https://` — POST a JSON payload.
+    Http {
+        https: bool,
+        host: String,
+        path: String,
+        query: Option<String>,
+    },
+    /// `file://` — append a log line.
+    File { path: String },
+    /// `exec://` — run a local hook command.
+    Exec { command: String },
+}
+
+impl NotifyTarget {
+    pub fn parse(raw: &str) -> Result<NotifyTarget, Error> {
+        let (scheme, rest) = match raw.find("://") {
+            Some(idx) => (&raw[..idx], &raw[idx + 3..]),
+            None => return Err(Error {
+                desc: "notify_url must include a scheme ('http://', 'https://', 'file://', or 'exec://')",
+                subject: None,
+            }),
+        };
+
+        match scheme {
+            "http" | "https" => {
+                let (host, path_and_query) = match rest.find('/') {
+                    Some(idx) => (&rest[..idx], &rest[idx..]),
+                    None => (rest, "/"),
+                };
+                if host.is_empty() {
+                    return Err(Error { desc: "notify_url is missing a host", subject: None });
+                }
+                let (path, query) = match path_and_query.find('?') {
+                    Some(idx) => (&path_and_query[..idx], Some(String::from(&path_and_query[idx + 1..]))),
+                    None => (path_and_query, None),
+                };
+                Ok(NotifyTarget::Http {
+                    https: scheme == "https",
+                    host: String::from(host),
+                    path: String::from(path),
+                    query: query,
+                })
+            }
+            "file" => {
+                if rest.is_empty() {
+                    return Err(Error { desc: "file:// notify_url is missing a path", subject: None });
+                }
+                Ok(NotifyTarget::File { path: String::from(rest) })
+            }
+            "exec" => {
+                if rest.is_empty() {
+                    return Err(Error { desc: "exec:// notify_url is missing a command", subject: None });
+                }
+                Ok(NotifyTarget::Exec { command: String::from(rest) })
+            }
+            _ => Err(Error {
+                desc: "unsupported notify_url scheme, expected 'http', 'https', 'file', or 'exec'",
+                subject: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_http() {
+        let target = NotifyTarget::parse("http://example.org").unwrap();
+        assert_eq!(target, NotifyTarget::Http {
+            https: false,
+            host: String::from("example.org"),
+            path: String::from("/"),
+            query: None,
+        });
+    }
+
+    #[test]
+    fn parses_https_with_path_and_query() {
+        let target = NotifyTarget::parse("https://example.org/hooks/deploy?token=abc").unwrap();
+        assert_eq!(target, NotifyTarget::Http {
+            https: true,
+            host: String::from("example.org"),
+            path: String::from("/hooks/deploy"),
+            query: Some(String::from("token=abc")),
+        });
+    }
+
+    #[test]
+    fn parses_file() {
+        let target = NotifyTarget::parse("file:///var/log/deploys.log").unwrap();
+        assert_eq!(target, NotifyTarget::File { path: String::from("/var/log/deploys.log") });
+    }
+
+    #[test]
+    fn parses_exec() {
+        let target = NotifyTarget::parse("exec://run-smoke-tests").unwrap();
+        assert_eq!(target, NotifyTarget::Exec { command: String::from("run-smoke-tests") });
+    }
+
+    #[test]
+    fn rejects_missing_scheme() {
+        let err = NotifyTarget::parse("example.org").unwrap_err();
+        assert_eq!(err.desc, "notify_url must include a scheme ('http://', 'https://', 'file://', or 'exec://')");
+    }
+
+    #[test]
+    fn rejects_empty_host() {
+        let err = NotifyTarget::parse("http:///path").unwrap_err();
+        assert_eq!(err.desc, "notify_url is missing a host");
+    }
+
+    #[test]
+    fn rejects_empty_file_path() {
+        let err = NotifyTarget::parse("file://").unwrap_err();
+        assert_eq!(err.desc, "file:// notify_url is missing a path");
+    }
+
+    #[test]
+    fn rejects_empty_exec_command() {
+        let err = NotifyTarget::parse("exec://").unwrap_err();
+        assert_eq!(err.desc, "exec:// notify_url is missing a command");
+    }
+
+    #[test]
+    fn rejects_unsupported_scheme() {
+        let err = NotifyTarget::parse("ftp://example.org").unwrap_err();
+        assert_eq!(err.desc, "unsupported notify_url scheme, expected 'http', 'https', 'file', or 'exec'");
+    }
+}
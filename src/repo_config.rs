@@ -3,69 +3,173 @@ use std::fs::File;
 use std::io::Read;
 use std::path::Path;
 use std::collections::BTreeMap;
-use std::string::ToString;
 use ::make_task::MakeTask;
-use ::ansible_task::AnsibleTask;
-use ::verified_path::VerifiedPath;
 use ::error::Error;
-
-#[derive(Debug, Clone, PartialEq, Eq, Copy)]
-pub enum DeployMethod {
-    Ansible,
-    Makefile,
+use ::deploy_backend::DeployTask;
+use ::backend_registry::BackendRegistry;
+use ::branch_pattern::{self, BranchPattern};
+use ::interpolate::{interpolate, InterpolationContext};
+use ::suggest::suggest;
+use ::dependency_graph;
+use ::notify_target::NotifyTarget;
+
+const METHOD_SUGGESTION_THRESHOLD: usize = 3;
+
+fn invalid_method_error(value: &str, registry: &BackendRegistry, subject: String) -> Error {
+    let mut subject = format!("{}, valid values are {}", subject, valid_values_list(&registry.names()));
+    if let Some(closest) = suggest(registry.names(), value, METHOD_SUGGESTION_THRESHOLD) {
+        subject.push_str(&format!(" (did you mean '{}'?)", closest));
+    }
+    Error {
+        desc: "invalid method",
+        subject: Some(subject),
+    }
 }
-impl ToString for DeployMethod {
-    fn to_string(&self) -> String {
-        match *self {
-            DeployMethod::Ansible => String::from("ansible"),
-            DeployMethod::Makefile => String::from("makefile"),
-        }
+
+/// Renders `["ansible", "makefile"]` as `"'ansible' and 'makefile'"`, or
+/// `["ansible", "makefile", "shell"]` as `"'ansible', 'makefile' and 'shell'"`.
+fn valid_values_list(names: &[&str]) -> String {
+    let mut sorted = names.to_vec();
+    sorted.sort();
+    let quoted: Vec<String> = sorted.iter().map(|name| format!("'{}'", name)).collect();
+    match quoted.split_last() {
+        None => String::new(),
+        Some((last, rest)) if rest.is_empty() => last.clone(),
+        Some((last, rest)) => format!("{} and {}", rest.join(", "), last),
     }
 }
 
-#[derive(Debug)]
 pub struct BranchConfig<'a> {
-    pub method: DeployMethod,
-    make_task: Option<MakeTask<'a>>,
-    ansible_task: Option<AnsibleTask<'a>>,
-    notify_url: Option<URL>,
+    pub method: String,
+    task: Box<DeployTask + 'a>,
+    notify_targets: Vec<NotifyTarget>,
+    depends_on: Vec<String>,
 }
 impl<'a> BranchConfig<'a> {
-    pub fn make_task(&self) -> Option<&MakeTask<'a>> {
-        match self.make_task {
-            Some(ref t) => Some(t),
-            None => None,
-        }
+    pub fn task(&self) -> &DeployTask {
+        &*self.task
     }
-    pub fn ansible_task(&self) -> Option<&AnsibleTask<'a>> {
-        match self.ansible_task {
-            Some(ref t) => Some(t),
-            None => None,
-        }
+
+    pub fn depends_on(&self) -> &[String] {
+        &self.depends_on
+    }
+
+    pub fn notify_targets(&self) -> &[NotifyTarget] {
+        &self.notify_targets
+    }
+}
+
+// `task` is a trait object; third-party `DeployTask` impls aren't required
+// to be `Debug`, so describe it via `DeployTask::describe` instead of
+// deriving.
+impl<'a> ::std::fmt::Debug for BranchConfig<'a> {
+    fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+        f.debug_struct("BranchConfig")
+            .field("method", &self.method)
+            .field("task", &self.task.describe())
+            .field("notify_targets", &self.notify_targets)
+            .field("depends_on", &self.depends_on)
+            .finish()
     }
 }
 
 pub type BranchConfigMap<'a> = BTreeMap<String, BranchConfig<'a>>;
 
-// TODO: use https://crates.io/crates/url instead
-pub type URL = String;
+/// Branch-independent values from `[defaults]`, handed to every
+/// `DeployBackend` as fallbacks for fields a branch's own `[branches.*]`
+/// table doesn't specify. Of the built-in backends only `ansible` uses one
+/// (`playbook`); `task` and `notify_targets` are not applied to branches.
+#[derive(Debug)]
+pub struct Defaults<'a> {
+    pub task: Option<MakeTask<'a>>,
+    /// Kept unresolved (rather than a `VerifiedPath`) because it may
+    /// contain `${branch}`, which is only known once a branch is matched;
+    /// backends interpolate and verify it themselves.
+    pub playbook: Option<String>,
+    pub notify_targets: Vec<NotifyTarget>,
+}
 
 #[derive(Debug)]
 pub struct RepoConfig<'a> {
-    default_method: DeployMethod,
-    default_task: Option<MakeTask<'a>>,
-    default_playbook: Option<VerifiedPath>,
-    default_notify_url: Option<URL>,
+    default_method: String,
+    defaults: Defaults<'a>,
     branches: BranchConfigMap<'a>,
+    branch_patterns: Vec<BranchPattern>,
     project_root: &'a Path,
 }
 
 impl<'a> RepoConfig<'a> {
-    pub fn lookup_branch(&self, name: &String) -> Option<&BranchConfig<'a>> {
-        self.branches.get(name)
+    /// Looks up a branch by exact name first, then by the most specific
+    /// matching glob pattern among `[branches.*]` keys (e.g. `release/*`),
+    /// so a single pattern can cover a whole family of branches while an
+    /// exact key still takes precedence.
+    pub fn lookup_branch(&self, name: &str) -> Option<&BranchConfig<'a>> {
+        if let Some(config) = self.branches.get(name) {
+            return Some(config);
+        }
+        let pattern = branch_pattern::best_match(&self.branch_patterns, name)?;
+        self.branches.get(pattern.key())
+    }
+
+    /// Nearest configured branch key to `name` by edit distance, for a
+    /// caller to suggest after an exact/glob `lookup_branch` miss.
+    pub fn lookup_branch_suggestion(&self, name: &str) -> Option<&str> {
+        suggest(self.branches.keys().map(|k| k.as_str()), name, METHOD_SUGGESTION_THRESHOLD)
+    }
+
+    /// Ordered list of `BranchConfig`s a deploy runner should execute for
+    /// `branch`: its `depends_on` closure first (topologically sorted),
+    /// then the branch itself. Rejects cycles and dependencies on
+    /// branches that aren't configured.
+    pub fn resolution_order(&self, branch: &str) -> Result<Vec<&BranchConfig<'a>>, Error> {
+        // Resolve a branch name (e.g. an actual git branch like
+        // "release/42") to its configured `[branches.*]` key, the same way
+        // `lookup_branch` would. Every edge in the graph below goes
+        // through this too, so a `depends_on` entry that's only covered
+        // by a glob pattern resolves instead of erroring as "not
+        // configured".
+        let resolve = |name: &str| -> Option<String> {
+            if self.branches.contains_key(name) {
+                return Some(String::from(name));
+            }
+            branch_pattern::best_match(&self.branch_patterns, name).map(|p| String::from(p.key()))
+        };
+
+        let key = match resolve(branch) {
+            Some(key) => key,
+            None => return Err(Error {
+                desc: "'depends_on' references a branch that is not configured",
+                subject: Some(String::from(branch)),
+            }),
+        };
+
+        let mut edges: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (name, config) in self.branches.iter() {
+            let mut deps = Vec::with_capacity(config.depends_on.len());
+            for dep in config.depends_on.iter() {
+                match resolve(dep) {
+                    Some(resolved) => deps.push(resolved),
+                    // Left unresolved so `dependency_graph::resolution_order`
+                    // reports it as an undefined-branch error naming `dep`.
+                    None => deps.push(dep.clone()),
+                }
+            }
+            edges.insert(name.clone(), deps);
+        }
+
+        let order = dependency_graph::resolution_order(&edges, &key)?;
+        Ok(order.iter().map(|name| self.branches.get(name).unwrap()).collect())
     }
 
     pub fn load(project_root: &'a Path) -> Result<RepoConfig<'a>, Error> {
+        Self::load_with_registry(project_root, BackendRegistry::new())
+    }
+
+    /// Like `load`, but with a caller-supplied `BackendRegistry` instead of
+    /// the default (`ansible` + `makefile` only). Lets a host application
+    /// register its own `DeployBackend`s (e.g. `shell`, `docker`, `kubectl`)
+    /// without patching this module.
+    pub fn load_with_registry(project_root: &'a Path, registry: BackendRegistry) -> Result<RepoConfig<'a>, Error> {
         let config_path = project_root.join(".deployer.conf");
         let mut file = match File::open(&config_path) {
             Ok(file) => file,
@@ -81,10 +185,16 @@ impl<'a> RepoConfig<'a> {
                 subject: Some(String::from(config_path.to_str().unwrap())),
             })
         };
-        Self::from_str(&contents, project_root)
+        Self::from_str_with_registry(&contents, project_root, registry)
     }
 
     pub fn from_str(string: &str, project_root: &'a Path) -> Result<RepoConfig<'a>, Error> {
+        Self::from_str_with_registry(string, project_root, BackendRegistry::new())
+    }
+
+    /// Like `from_str`, but with a caller-supplied `BackendRegistry`. See
+    /// `load_with_registry`.
+    pub fn from_str_with_registry(string: &str, project_root: &'a Path, registry: BackendRegistry) -> Result<RepoConfig<'a>, Error> {
         let root = match toml::Parser::new(string).parse() {
             Some(value) => value,
             None => return Err(Error {
@@ -93,7 +203,7 @@ impl<'a> RepoConfig<'a> {
             }),
         };
 
-        let defaults = match root.get("defaults") {
+        let defaults_table = match root.get("defaults") {
             Some(value) => value,
             None => return Err(Error {
                 desc: "missing 'defaults' section",
@@ -101,54 +211,57 @@ impl<'a> RepoConfig<'a> {
             }),
         };
 
-        let default_method = match lookup_as_string(defaults, "method") {
-            LookupResult::Missing => DeployMethod::Makefile,
+        let default_method = match lookup_as_string(defaults_table, "method") {
+            LookupResult::Missing => String::from("makefile"),
             LookupResult::WrongType => return Err(Error {
                 desc: "could not read 'defaults.method' as string",
                 subject: Some(String::from("defaults.method")),
             }),
-            LookupResult::Value(v) => match v {
-                "ansible" => DeployMethod::Ansible,
-                "makefile" | "make" => DeployMethod::Makefile,
-                _ => return Err(Error {
-                    desc: "invalid type, valid values are 'ansible' and 'makefile'",
-                    subject: Some(String::from("defaults.method")),
-                }),
+            LookupResult::Value(v) => {
+                if registry.get(v).is_none() {
+                    return Err(invalid_method_error(v, &registry, String::from("defaults.method")));
+                }
+                String::from(v)
             }
         };
 
-        let default_task = match lookup_as_string(defaults, "task") {
+        let no_branch_ctx = InterpolationContext { branch: None, project_root: project_root };
+
+        let default_task = match lookup_as_string(defaults_table, "task") {
             LookupResult::Missing => None,
             LookupResult::WrongType => return Err(Error {
                 desc: "could not read 'defaults.task' as string",
                 subject: Some(String::from("defaults.task")),
             }),
-            LookupResult::Value(v) => match MakeTask::new(project_root, v) {
-                Ok(v) => Some(v),
-                Err(err) => return Err(err),
+            LookupResult::Value(v) => {
+                let v = match interpolate(v, &no_branch_ctx) {
+                    Ok(v) => v,
+                    Err(err) => return Err(Error { desc: err.desc, subject: Some(String::from("defaults.task")) }),
+                };
+                match MakeTask::new(project_root, &v) {
+                    Ok(v) => Some(v),
+                    Err(err) => return Err(err),
+                }
             }
         };
 
-        let default_playbook = match lookup_as_string(defaults, "playbook") {
+        // Left unresolved: may contain `${branch}`, interpolated per-branch
+        // by the backend that ends up using it as a fallback.
+        let default_playbook = match lookup_as_string(defaults_table, "playbook") {
             LookupResult::Missing => None,
             LookupResult::WrongType => return Err(Error {
                 desc: "could not read 'defaults.playbook' as string",
                 subject: Some(String::from("defaults.playbook")),
             }),
-            LookupResult::Value(v) =>
-                match VerifiedPath::file(Some(project_root), Path::new(v)) {
-                    Ok(v) => Some(v),
-                    Err(err) => return Err(err),
-                },
+            LookupResult::Value(v) => Some(String::from(v)),
         };
 
-        let default_notify_url = match lookup_as_string(defaults, "notify_url") {
-            LookupResult::Missing => None,
-            LookupResult::WrongType => return Err(Error {
-                desc: "could not read 'defaults.notify_url' as string",
-                subject: Some(String::from("defaults.notify_url")),
-            }),
-            LookupResult::Value(v) => Some(v.to_string()),
+        let default_notify_targets = parse_notify_targets(defaults_table, &no_branch_ctx, "defaults")?;
+
+        let defaults = Defaults {
+            task: default_task,
+            playbook: default_playbook,
+            notify_targets: default_notify_targets,
         };
 
         let raw_branches = match root.get("branches") {
@@ -166,8 +279,13 @@ impl<'a> RepoConfig<'a> {
         };
 
         let mut branches = BranchConfigMap::new();
+        let mut branch_patterns = Vec::new();
 
         for (key, table) in raw_branches.iter() {
+            if let Some(pattern) = BranchPattern::new(key) {
+                branch_patterns.push(pattern);
+            }
+
             if table.as_table().is_none() {
                 return Err(Error {
                     desc: "every 'branches' must be a table",
@@ -176,111 +294,109 @@ impl<'a> RepoConfig<'a> {
             }
 
             let method = match lookup_as_string(table, "method") {
-                LookupResult::Missing => default_method,
+                LookupResult::Missing => default_method.clone(),
                 LookupResult::WrongType => return Err(Error {
-                    desc: "could not read 'defaults.method' as string",
-                    subject: Some(String::from("defaults.method")),
+                    desc: "could not read 'branch.method' as string",
+                    subject: Some(format!("branch.{}.method", key)),
                 }),
-                LookupResult::Value(v) => match v {
-                    "ansible" => DeployMethod::Ansible,
-                    "makefile" | "make" => DeployMethod::Makefile,
-                    _ => return Err(Error {
-                        desc: "invalid type, valid values are 'ansible' and 'makefile'",
-                        subject: Some(String::from("defaults.method")),
-                    }),
-                }
+                LookupResult::Value(v) => String::from(v),
             };
 
-            let playbook = match lookup_as_string(table, "playbook") {
-                LookupResult::Missing => None,
-                LookupResult::WrongType => return Err(Error {
-                    desc: "branch 'playbook' not a string",
-                    subject: Some(format!("branch.{}.playbook", key)),
-                }),
-                LookupResult::Value(v) =>
-                    match VerifiedPath::file(Some(project_root), Path::new(v)) {
-                        Ok(v) => Some(v),
-                        Err(err) => return Err(err),
-                    },
+            let backend = match registry.get(&method) {
+                Some(backend) => backend,
+                None => return Err(invalid_method_error(&method, &registry, format!("branch.{}.method", key))),
             };
-            let inventory = match lookup_as_string(table, "inventory") {
-                LookupResult::Missing => None,
-                LookupResult::WrongType => return Err(Error {
-                    desc: "branch 'inventory' not a string",
-                    subject: Some(format!("branch.{}.inventory", key)),
+
+            let task = match backend.parse_branch(key, table, project_root, &defaults) {
+                Ok(task) => task,
+                Err(err) => return Err(Error {
+                    desc: err.desc,
+                    subject: err.subject.or_else(|| Some(format!("branch.{}", key))),
                 }),
-                LookupResult::Value(v) =>
-                    match VerifiedPath::file(Some(project_root), Path::new(v)) {
-                        Ok(v) => Some(v),
-                        Err(err) => return Err(err),
-                    },
             };
 
-            let ansible_task = if method == DeployMethod::Ansible {
-                match (playbook, inventory, default_playbook.clone()) {
-                    (Some(p), Some(i), _) |
-                    (None, Some(i), Some(p)) => Some(AnsibleTask::new(p.to_string(), i.to_string(), &project_root)),
-                    (_, _, _) => return Err(Error {
-                        desc: "could not combine default and branch config to find playbook + inventory combination",
-                        subject: Some(format!("branch.{}", key)),
-                    })
-                }
-            } else { None };
-
-            let make_task = if method == DeployMethod::Makefile {
-                match lookup_as_string(table, "task") {
-                    LookupResult::Missing => None,
-                    LookupResult::WrongType => return Err(Error {
-                        desc: "branch 'task' not a string",
-                        subject: Some(format!("branch.{}.task", key)),
-                    }),
-                    LookupResult::Value(v) => match MakeTask::new(project_root, v) {
-                        Ok(v) => Some(v),
-                        Err(err) => return Err(err),
-                    }
-                }
-            } else { None };
+            let depends_on = lookup_as_string_list(table, "depends_on", format!("branch.{}.depends_on", key))?;
 
-            if make_task.is_none() && ansible_task.is_none() {
-                return Err(Error {
-                    desc: "cannot construct a task for branch between local config and defaults",
-                    subject: Some(format!("branch.{}", key)),
-                })
-            }
+            let branch_ctx = InterpolationContext { branch: Some(key.as_str()), project_root: project_root };
+            let notify_targets = parse_notify_targets(table, &branch_ctx, &format!("branch.{}", key))?;
 
             branches.insert(key.clone(), BranchConfig {
-                ansible_task: ansible_task,
-                make_task: make_task,
                 method: method,
-                notify_url: match lookup_as_string(table, "notify_url") {
-                    LookupResult::Missing => None,
-                    LookupResult::WrongType => return Err(Error {
-                        desc: "branch 'notify_url' not a string",
-                        subject: Some(format!("branch.{}.notify_url", key)),
-                    }),
-                    LookupResult::Value(v) => Some(v.to_string()),
-                },
+                task: task,
+                depends_on: depends_on,
+                notify_targets: notify_targets,
             });
         }
 
         Ok(RepoConfig {
             default_method: default_method,
-            default_task: default_task,
-            default_playbook: default_playbook,
-            default_notify_url: default_notify_url,
+            defaults: defaults,
             branches: branches,
+            branch_patterns: branch_patterns,
             project_root: project_root,
         })
     }
 }
 
-enum LookupResult<'a> {
+pub(crate) enum LookupResult<'a> {
     Missing,
     WrongType,
     Value(&'a str),
 }
 
-fn lookup_as_string<'a>(obj: &'a toml::Value, key: &'static str) -> LookupResult<'a> {
+/// Collects `notify_url` (a single string) and `notify_urls` (an array)
+/// from `table`, interpolates each against `ctx`, and parses them into
+/// validated `NotifyTarget`s. `subject` is the `defaults` or `branch.<key>`
+/// prefix used in error subjects.
+fn parse_notify_targets(table: &toml::Value, ctx: &InterpolationContext, subject: &str) -> Result<Vec<NotifyTarget>, Error> {
+    let mut raw = Vec::new();
+
+    match lookup_as_string(table, "notify_url") {
+        LookupResult::Missing => {}
+        LookupResult::WrongType => return Err(Error {
+            desc: "'notify_url' not a string",
+            subject: Some(format!("{}.notify_url", subject)),
+        }),
+        LookupResult::Value(v) => raw.push(String::from(v)),
+    }
+
+    raw.extend(lookup_as_string_list(table, "notify_urls", format!("{}.notify_urls", subject))?);
+
+    let mut targets = Vec::with_capacity(raw.len());
+    for url in raw {
+        let url = interpolate(&url, ctx).map_err(|err| Error {
+            desc: err.desc,
+            subject: Some(format!("{}.notify_url", subject)),
+        })?;
+        let target = NotifyTarget::parse(&url).map_err(|err| Error {
+            desc: err.desc,
+            subject: Some(format!("{}.notify_url", subject)),
+        })?;
+        targets.push(target);
+    }
+    Ok(targets)
+}
+
+fn lookup_as_string_list(obj: &toml::Value, key: &'static str, subject: String) -> Result<Vec<String>, Error> {
+    match obj.lookup(key) {
+        None => Ok(Vec::new()),
+        Some(v) => match v.as_slice() {
+            None => Err(Error { desc: "must be an array of strings", subject: Some(subject) }),
+            Some(items) => {
+                let mut out = Vec::with_capacity(items.len());
+                for item in items {
+                    match item.as_str() {
+                        Some(s) => out.push(String::from(s)),
+                        None => return Err(Error { desc: "must be an array of strings", subject: Some(subject) }),
+                    }
+                }
+                Ok(out)
+            }
+        }
+    }
+}
+
+pub(crate) fn lookup_as_string<'a>(obj: &'a toml::Value, key: &'static str) -> LookupResult<'a> {
     match obj.lookup(key) {
         None => LookupResult::Missing,
         Some(v) => {
@@ -322,41 +438,37 @@ mod tests {
         let config = RepoConfig::load(project_root).unwrap();
         println!("{:?}", config);
 
-        assert_eq!(config.default_method.to_string(), "ansible");
-        assert!(config.default_task.is_some());
-        assert_eq!(config.default_task.unwrap().to_string(), "deploy");
-        assert!(config.default_playbook.is_some());
-        assert_eq!(config.default_playbook.unwrap().path(), Path::new("ansible/deploy.yml"));
-        assert!(config.default_notify_url.is_none());
+        assert_eq!(config.default_method, "ansible");
+        assert!(config.defaults.task.is_some());
+        assert_eq!(config.defaults.task.unwrap().to_string(), "deploy");
+        assert!(config.defaults.playbook.is_some());
+        assert_eq!(config.defaults.playbook.unwrap(), "ansible/deploy.yml");
+        assert!(config.defaults.notify_targets.is_empty());
 
         // production config
         {
             let config = config.branches.get("production").unwrap();
-            let ref ansible_task = config.ansible_task().unwrap();
-            assert_eq!(ansible_task.playbook, "ansible/production.yml");
-            assert_eq!(ansible_task.inventory, "ansible/inventory/production");
-            assert_eq!(config.method, DeployMethod::Ansible);
-            assert!(config.make_task.is_none());
-            assert!(config.notify_url.is_none());
+            assert_eq!(config.method, "ansible");
+            assert_eq!(config.task().describe(), "ansible playbook=ansible/production.yml inventory=ansible/inventory/production");
+            assert!(config.notify_targets().is_empty());
         }
         // staging config
         {
             let config = config.branches.get("staging").unwrap();
-            let notify_url = config.notify_url.clone().unwrap();
-            let ansible_task = config.ansible_task().unwrap();
-            assert_eq!(ansible_task.inventory, "ansible/inventory/staging");
-            assert_eq!(ansible_task.playbook, "ansible/deploy.yml");
-            assert_eq!(config.method, DeployMethod::Ansible);
-            assert!(config.make_task.is_none());
-            assert_eq!(notify_url, "http://example.org");
+            assert_eq!(config.method, "ansible");
+            assert_eq!(config.task().describe(), "ansible playbook=ansible/deploy.yml inventory=ansible/inventory/staging");
+            assert_eq!(config.notify_targets(), &[NotifyTarget::Http {
+                https: false,
+                host: String::from("example.org"),
+                path: String::from("/"),
+                query: None,
+            }]);
         }
         // brian-test-branch config
         {
             let config = config.branches.get("brian-test-branch").unwrap();
-            let method = config.method.clone();
-            assert!(config.ansible_task.is_none());
-            assert_eq!(method.to_string(), "makefile");
-            assert!(config.notify_url.is_none());
+            assert_eq!(config.method, "makefile");
+            assert!(config.notify_targets().is_empty());
         }
 
     }
@@ -0,0 +1,86 @@
+/// Standard DP edit-distance between two strings, the same technique
+/// cargo uses to suggest corrections for mistyped subcommands.
+pub fn lev_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut prev_diag = row[0];
+        row[0] = i + 1;
+        for (j, &b_char) in b_chars.iter().enumerate() {
+            let deleted = row[j] + 1;
+            let inserted = row[j + 1] + 1;
+            let substituted = prev_diag + if a_char == b_char { 0 } else { 1 };
+            prev_diag = row[j + 1];
+            row[j + 1] = deleted.min(inserted).min(substituted);
+        }
+    }
+
+    row[b_chars.len()]
+}
+
+/// Closest candidate to `input` by edit distance, if it's within
+/// `max_distance`. Used to turn "invalid value" errors into "did you
+/// mean '<x>'?" hints.
+pub fn suggest<'c, I>(candidates: I, input: &str, max_distance: usize) -> Option<&'c str>
+    where I: IntoIterator<Item = &'c str>
+{
+    candidates.into_iter()
+        .map(|candidate| (candidate, lev_distance(input, candidate)))
+        .filter(|&(_, distance)| distance <= max_distance)
+        .min_by_key(|&(_, distance)| distance)
+        .map(|(candidate, _)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lev_distance_of_identical_strings_is_zero() {
+        assert_eq!(lev_distance("ansible", "ansible"), 0);
+    }
+
+    #[test]
+    fn lev_distance_against_empty_string_is_the_length() {
+        assert_eq!(lev_distance("", "ansible"), 7);
+        assert_eq!(lev_distance("ansible", ""), 7);
+    }
+
+    #[test]
+    fn lev_distance_classic_kitten_sitting() {
+        assert_eq!(lev_distance("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn lev_distance_is_symmetric() {
+        assert_eq!(lev_distance("makefil", "makefile"), lev_distance("makefile", "makefil"));
+    }
+
+    #[test]
+    fn suggest_picks_the_closest_candidate_within_threshold() {
+        let candidates = vec!["ansible", "makefile"];
+        assert_eq!(suggest(candidates, "makefil", 3), Some("makefile"));
+    }
+
+    #[test]
+    fn suggest_returns_none_when_nothing_is_within_threshold() {
+        let candidates = vec!["ansible", "makefile"];
+        assert_eq!(suggest(candidates, "zzzzzzzzzz", 3), None);
+    }
+
+    #[test]
+    fn suggest_breaks_ties_by_candidate_order() {
+        // "ab" and "ba" are both distance 2 from "xy"; the first candidate
+        // in iteration order wins the tie.
+        let candidates = vec!["ab", "ba"];
+        assert_eq!(suggest(candidates, "xy", 2), Some("ab"));
+    }
+
+    #[test]
+    fn suggest_respects_an_exact_threshold_boundary() {
+        let candidates = vec!["makefile"];
+        assert_eq!(suggest(candidates.clone(), "makefil", 1), Some("makefile"));
+        assert_eq!(suggest(candidates, "make", 3), None);
+    }
+}